@@ -1,8 +1,8 @@
-use regex::Regex;
+use aho_corasick::{AhoCorasick, MatchKind};
 use std::env;
 use std::fs;
-use std::io::{self, BufRead, Write};
-use std::path::{Path, PathBuf};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
 
 const OSC: &str = "\x1b]";
 const BEL: &str = "\x07";
@@ -11,61 +11,303 @@ fn make_hyperlink(url: &str, text: &str) -> String {
     format!("{OSC}8;;{url}{BEL}{text}{OSC}8;;{BEL}")
 }
 
-fn build_pattern(prefixes: &[String]) -> String {
-    format!(r#"(?:{})(?:/[^$\s;~:"\x1b]+)?"#, prefixes.join("|"))
+/// Controls whether OSC 8 escapes are emitted at all.
+///
+/// Unconditionally injecting hyperlink escapes corrupts output once stdout
+/// stops being a terminal, e.g. when it's redirected to a file or piped into
+/// another program. `Auto` (the default) checks `IsTerminal` on stdout so the
+/// binary is safe to leave in a pipeline unconditionally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HyperlinkMode {
+    Auto,
+    Always,
+    Never,
+}
+
+impl HyperlinkMode {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    fn should_emit(self, stdout_is_terminal: bool) -> bool {
+        match self {
+            Self::Auto => stdout_is_terminal,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Parses `--hyperlink=auto|always|never` out of the process arguments,
+/// defaulting to `auto` when the flag isn't present.
+fn parse_hyperlink_mode<I: IntoIterator<Item = String>>(args: I) -> HyperlinkMode {
+    let mut mode = HyperlinkMode::Auto;
+    for arg in args {
+        if let Some(value) = arg.strip_prefix("--hyperlink=") {
+            mode = HyperlinkMode::from_str(value).unwrap_or_else(|| {
+                eprintln!("error: invalid value for --hyperlink: {value} (expected auto, always, or never)");
+                std::process::exit(2);
+            });
+        }
+    }
+    mode
+}
+
+/// Characters that can appear in the path segment following a recognized
+/// prefix. Mirrors the old `[^$\s;~:"\x1b]` regex character class. `\` is
+/// left unexcluded so Windows-style separators are consumed as part of the
+/// path body alongside `/`. Windows paths routinely contain literal spaces
+/// (`C:\Program Files\`), so a plain space is allowed through on that
+/// platform only; other whitespace (and POSIX paths, which aren't normally
+/// space-delimited on the command line) keep stopping the match.
+fn is_path_body_char(c: char) -> bool {
+    if c.is_whitespace() {
+        return cfg!(windows) && c == ' ';
+    }
+    !matches!(c, '$' | ';' | '~' | ':' | '"' | '\x1b')
+}
+
+/// Strips the `\\?\` verbatim-path prefix Windows attaches to some
+/// canonicalized paths, the same normalization fd's `absolute_path` applies
+/// before turning a path into something user (and URL) facing.
+fn strip_verbatim_prefix(path: &str) -> &str {
+    path.strip_prefix(r"\\?\").unwrap_or(path)
+}
+
+/// Percent-encodes the characters RFC 8089 reserves out of a `file://` URL
+/// path, plus spaces, without touching `/` or `:`.
+fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => encoded.push_str("%20"),
+            '"' => encoded.push_str("%22"),
+            '#' => encoded.push_str("%23"),
+            '%' => encoded.push_str("%25"),
+            '<' => encoded.push_str("%3C"),
+            '>' => encoded.push_str("%3E"),
+            '?' => encoded.push_str("%3F"),
+            '[' => encoded.push_str("%5B"),
+            ']' => encoded.push_str("%5D"),
+            '^' => encoded.push_str("%5E"),
+            '`' => encoded.push_str("%60"),
+            '{' => encoded.push_str("%7B"),
+            '|' => encoded.push_str("%7C"),
+            '}' => encoded.push_str("%7D"),
+            _ => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+/// Builds the `file://` URL for an absolute path, branching on platform:
+/// Windows paths get their verbatim prefix stripped, backslashes turned into
+/// forward slashes, and reserved characters percent-encoded so the result is
+/// a valid RFC 8089 file URL (`file://host/C:/Users/name/a%20file.txt`).
+fn build_file_url(hostname: &str, abs_path: &str) -> String {
+    if cfg!(windows) {
+        let stripped = strip_verbatim_prefix(abs_path);
+        let normalized = stripped.replace('\\', "/");
+        let encoded = percent_encode_path(&normalized);
+        if encoded.starts_with('/') {
+            format!("file://{hostname}{encoded}")
+        } else {
+            format!("file://{hostname}/{encoded}")
+        }
+    } else {
+        format!("file://{hostname}{abs_path}")
+    }
+}
+
+/// Builds the Aho-Corasick automaton used to locate prefix occurrences.
+///
+/// This replaces the old approach of compiling every prefix into a single
+/// regex alternation: for directories with thousands of entries that
+/// produced a pathological pattern that was slow to both compile and match.
+/// Aho-Corasick matches all prefixes in a single linear pass regardless of
+/// how many there are, the same tradeoff ripgrep made when it moved literal
+/// matching in glob sets off the regex engine.
+fn build_matcher(prefixes: &[String]) -> AhoCorasick {
+    AhoCorasick::builder()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(prefixes)
+        .expect("failed to build prefix matcher")
+}
+
+/// Parses a single `:<digits>` group starting at `s`, returning the parsed
+/// number and how many bytes (including the leading `:`) it consumed.
+fn parse_colon_number(s: &str) -> Option<(u32, usize)> {
+    let digits = s.strip_prefix(':')?;
+    let digit_len = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+    if digit_len == 0 {
+        return None;
+    }
+    digits[..digit_len].parse().ok().map(|n| (n, 1 + digit_len))
+}
+
+/// Recognizes a grep-style `:line` or `:line:col` suffix right after a
+/// matched path (e.g. the `:42:10` in `src/main.rs:42:10: error`), so output
+/// like grep/ripgrep/compiler diagnostics gets a hyperlink anchored to the
+/// exact location instead of just the file.
+fn parse_location_suffix(s: &str) -> (Option<u32>, Option<u32>, usize) {
+    let Some((line_no, consumed)) = parse_colon_number(s) else {
+        return (None, None, 0);
+    };
+    match parse_colon_number(&s[consumed..]) {
+        Some((col_no, col_consumed)) => (Some(line_no), Some(col_no), consumed + col_consumed),
+        None => (Some(line_no), None, consumed),
+    }
+}
+
+/// Builds the URL suffix for a parsed line/column location. A bare line
+/// number becomes a `#L<line>` fragment; a line and column are folded
+/// directly into the path as `:<line>:<col>`, matching how editors resolve
+/// `path:line:col` style references.
+fn location_url_suffix(line_no: Option<u32>, col_no: Option<u32>) -> String {
+    match (line_no, col_no) {
+        (Some(line_no), Some(col_no)) => format!(":{line_no}:{col_no}"),
+        (Some(line_no), None) => format!("#L{line_no}"),
+        (None, _) => String::new(),
+    }
 }
 
 fn process_line(
     line: &str,
-    re: &Regex,
+    matcher: &AhoCorasick,
     hostname: &str,
     home: &str,
     cwd: &Path,
 ) -> String {
-    re.replace_all(line, |caps: &regex::Captures| {
-        let matched = &caps[0];
+    let mut result = String::with_capacity(line.len());
+    let mut last_end = 0;
+
+    for m in matcher.find_iter(line) {
+        let start = m.start();
+        if start < last_end {
+            // Overlaps a span already consumed as part of a previous match.
+            continue;
+        }
+
+        // A prefix only counts as a path start at a boundary: either the
+        // start of the line, or a character that isn't itself part of a
+        // path body (so "src" inside "describe" isn't matched).
+        let prev_char = line[..start].chars().next_back();
+        if prev_char.is_some_and(is_path_body_char) {
+            continue;
+        }
+
+        // Consume the trailing path segment(s) after the matched prefix,
+        // but only when a real separator follows. Without this, "library"
+        // would glue onto the cwd-entry prefix "lib" and turn a plain word
+        // into a bogus hyperlink; requiring a leading separator mirrors the
+        // old regex's `(?:/[^...]+)?` suffix group, which never continued a
+        // match without first seeing a `/`.
+        //
+        // The UNC root `\\` is the one prefix that's exempt from this: it's
+        // not a directory name in its own right, so the server name right
+        // after it (`\\server\share\...`) must always be consumed, separator
+        // or not.
+        let matched_prefix = &line[start..m.end()];
+        let is_unc_root = cfg!(windows) && matched_prefix == r"\\";
+        let next_is_separator = line[m.end()..]
+            .chars()
+            .next()
+            .is_some_and(|c| c == '/' || (cfg!(windows) && c == '\\'));
+
+        let mut end = m.end();
+        if is_unc_root || next_is_separator {
+            for c in line[m.end()..].chars() {
+                if is_path_body_char(c) {
+                    end += c.len_utf8();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        let path_text = &line[start..end];
 
-        // Expand ~ to home directory
-        let expanded = if matched.starts_with("~/") {
-            format!("{}{}", home, &matched[1..])
+        // Recognize a trailing grep-style `:line` or `:line:col` suffix
+        // (`src/main.rs:42:10: error`) and fold it into the visible match.
+        let (line_no, col_no, suffix_len) = parse_location_suffix(&line[end..]);
+        let full_end = end + suffix_len;
+        let matched = &line[start..full_end];
+
+        // Expand ~ to home directory. Windows paths may use `~\` as well as
+        // the POSIX `~/`.
+        let expanded = if path_text.starts_with("~/")
+            || (cfg!(windows) && path_text.starts_with("~\\"))
+        {
+            format!("{}{}", home, &path_text[1..])
         } else {
-            matched.to_string()
+            path_text.to_string()
         };
 
-        // Convert to absolute path
+        // Convert to absolute path. `Path::is_absolute` already understands
+        // drive-letter and UNC roots when compiled for Windows.
         let abs_path = if Path::new(&expanded).is_absolute() {
             expanded
         } else {
             cwd.join(&expanded).to_string_lossy().into_owned()
         };
 
-        let url = format!("file://{}{}", hostname, abs_path);
-        make_hyperlink(&url, matched)
-    })
-    .into_owned()
+        let url = format!(
+            "{}{}",
+            build_file_url(hostname, &abs_path),
+            location_url_suffix(line_no, col_no)
+        );
+
+        result.push_str(&line[last_end..start]);
+        result.push_str(&make_hyperlink(&url, matched));
+        last_end = full_end;
+    }
+
+    result.push_str(&line[last_end..]);
+    result
 }
 
-fn get_prefixes(cwd: &Path) -> Vec<String> {
-    let mut prefixes: Vec<String> = vec![
+/// The root prefixes recognized before any directory entries are added.
+/// Windows has no equivalent of the POSIX FHS, so it's matched on drive
+/// letters (`C:`, consuming the following `\` or `/` as part of the path
+/// body) and the `\\` that starts a UNC path instead.
+#[cfg(not(windows))]
+fn root_prefixes() -> Vec<String> {
+    [
         "/bin", "/boot", "/dev", "/etc", "/home", "/lib", "/lib64",
         "/lost+found", "/mnt", "/opt", "/proc", "/root", "/run",
         "/sbin", "/srv", "/sys", "/tmp", "/usr", "/var",
     ]
     .into_iter()
-    .map(|s| regex::escape(s))
-    .collect();
+    .map(String::from)
+    .collect()
+}
+
+#[cfg(windows)]
+fn root_prefixes() -> Vec<String> {
+    let mut prefixes: Vec<String> = ('A'..='Z').map(|letter| format!("{letter}:")).collect();
+    prefixes.push(r"\\".to_string());
+    prefixes
+}
+
+fn get_prefixes(cwd: &Path) -> Vec<String> {
+    let mut prefixes: Vec<String> = root_prefixes();
 
     // Add current directory entries as relative path prefixes
     if let Ok(entries) = fs::read_dir(cwd) {
         for entry in entries.flatten() {
             if let Some(name) = entry.file_name().to_str() {
-                prefixes.push(regex::escape(name));
+                prefixes.push(name.to_string());
             }
         }
     }
 
     // Add home directory prefix
-    prefixes.push(regex::escape("~"));
+    prefixes.push("~".to_string());
 
     prefixes
 }
@@ -75,20 +317,33 @@ fn main() -> io::Result<()> {
         .map(|h| h.to_string_lossy().into_owned())
         .unwrap_or_else(|_| "localhost".to_string());
 
-    let home = env::var("HOME").unwrap_or_default();
+    // Windows has no `HOME` by convention; fall back to `USERPROFILE`.
+    let home = env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .unwrap_or_default();
     let cwd = env::current_dir()?;
 
-    let prefixes = get_prefixes(&cwd);
-    let pattern = build_pattern(&prefixes);
-    let re = Regex::new(&pattern).expect("Invalid regex");
+    let mode = parse_hyperlink_mode(env::args().skip(1));
 
     let stdin = io::stdin();
     let stdout = io::stdout();
     let mut stdout = stdout.lock();
 
+    let emit_hyperlinks = mode.should_emit(stdout.is_terminal());
+
+    if !emit_hyperlinks {
+        for line in stdin.lock().lines() {
+            writeln!(stdout, "{}", line?)?;
+        }
+        return Ok(());
+    }
+
+    let prefixes = get_prefixes(&cwd);
+    let matcher = build_matcher(&prefixes);
+
     for line in stdin.lock().lines() {
         let line = line?;
-        let result = process_line(&line, &re, &hostname, &home, &cwd);
+        let result = process_line(&line, &matcher, &hostname, &home, &cwd);
         writeln!(stdout, "{}", result)?;
     }
 
@@ -98,15 +353,15 @@ fn main() -> io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
 
-    fn test_regex() -> Regex {
-        let prefixes = vec![
-            regex::escape("/tmp"),
-            regex::escape("/home"),
-            regex::escape("src"),
-            regex::escape("~"),
-        ];
-        Regex::new(&build_pattern(&prefixes)).unwrap()
+    fn test_matcher() -> AhoCorasick {
+        build_matcher(&[
+            "/tmp".to_string(),
+            "/home".to_string(),
+            "src".to_string(),
+            "~".to_string(),
+        ])
     }
 
     #[test]
@@ -117,36 +372,36 @@ mod tests {
 
     #[test]
     fn test_absolute_path() {
-        let re = test_regex();
+        let matcher = test_matcher();
         let cwd = PathBuf::from("/work");
-        let result = process_line("/tmp/test.txt", &re, "host", "/home/user", &cwd);
+        let result = process_line("/tmp/test.txt", &matcher, "host", "/home/user", &cwd);
         assert!(result.contains("file://host/tmp/test.txt"));
         assert!(result.contains("\x1b]8;;"));
     }
 
     #[test]
     fn test_relative_path() {
-        let re = test_regex();
+        let matcher = test_matcher();
         let cwd = PathBuf::from("/work");
-        let result = process_line("src/main.rs", &re, "host", "/home/user", &cwd);
+        let result = process_line("src/main.rs", &matcher, "host", "/home/user", &cwd);
         assert!(result.contains("file://host/work/src/main.rs"));
     }
 
     #[test]
     fn test_home_expansion() {
-        let re = test_regex();
+        let matcher = test_matcher();
         let cwd = PathBuf::from("/work");
-        let result = process_line("~/documents/file.txt", &re, "host", "/home/user", &cwd);
+        let result = process_line("~/documents/file.txt", &matcher, "host", "/home/user", &cwd);
         assert!(result.contains("file://host/home/user/documents/file.txt"));
     }
 
     #[test]
     fn test_preserves_ansi_colors() {
-        let re = test_regex();
+        let matcher = test_matcher();
         let cwd = PathBuf::from("/work");
         // Simulates: \x1b[31mmodified: src/main.rs\x1b[m
         let input = "\x1b[31mmodified: src/main.rs\x1b[m";
-        let result = process_line(input, &re, "host", "/home/user", &cwd);
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
 
         // Should preserve color codes
         assert!(result.contains("\x1b[31m"));
@@ -159,20 +414,166 @@ mod tests {
 
     #[test]
     fn test_no_path_unchanged() {
-        let re = test_regex();
+        let matcher = test_matcher();
         let cwd = PathBuf::from("/work");
         let input = "just some text without paths";
-        let result = process_line(input, &re, "host", "/home/user", &cwd);
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
         assert_eq!(result, input);
     }
 
     #[test]
     fn test_multiple_paths() {
-        let re = test_regex();
+        let matcher = test_matcher();
         let cwd = PathBuf::from("/work");
         let input = "comparing /tmp/a.txt and /tmp/b.txt";
-        let result = process_line(input, &re, "host", "/home/user", &cwd);
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
         // Should have two hyperlinks
         assert_eq!(result.matches("\x1b]8;;file://").count(), 2);
     }
+
+    #[test]
+    fn test_hyperlink_mode_defaults_to_auto() {
+        let mode = parse_hyperlink_mode(Vec::<String>::new());
+        assert_eq!(mode, HyperlinkMode::Auto);
+    }
+
+    #[test]
+    fn test_hyperlink_mode_parses_flag() {
+        let args = vec!["--hyperlink=always".to_string()];
+        assert_eq!(parse_hyperlink_mode(args), HyperlinkMode::Always);
+
+        let args = vec!["--hyperlink=never".to_string()];
+        assert_eq!(parse_hyperlink_mode(args), HyperlinkMode::Never);
+    }
+
+    #[test]
+    fn test_hyperlink_mode_should_emit() {
+        assert!(HyperlinkMode::Always.should_emit(false));
+        assert!(!HyperlinkMode::Never.should_emit(true));
+        assert!(HyperlinkMode::Auto.should_emit(true));
+        assert!(!HyperlinkMode::Auto.should_emit(false));
+    }
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        assert_eq!(strip_verbatim_prefix(r"\\?\C:\Users\name"), r"C:\Users\name");
+        assert_eq!(strip_verbatim_prefix(r"C:\Users\name"), r"C:\Users\name");
+    }
+
+    #[test]
+    fn test_percent_encode_path() {
+        assert_eq!(percent_encode_path("/C:/Users/a name.txt"), "/C:/Users/a%20name.txt");
+        assert_eq!(percent_encode_path("/tmp/plain.txt"), "/tmp/plain.txt");
+    }
+
+    #[test]
+    fn test_build_file_url_posix() {
+        assert_eq!(build_file_url("host", "/tmp/test.txt"), "file://host/tmp/test.txt");
+    }
+
+    #[test]
+    fn test_parse_location_suffix_line_only() {
+        assert_eq!(parse_location_suffix(": error"), (None, None, 0));
+        assert_eq!(parse_location_suffix(":42: error"), (Some(42), None, 3));
+    }
+
+    #[test]
+    fn test_parse_location_suffix_line_and_col() {
+        assert_eq!(parse_location_suffix(":42:10: error"), (Some(42), Some(10), 6));
+    }
+
+    #[test]
+    fn test_location_url_suffix() {
+        assert_eq!(location_url_suffix(None, None), "");
+        assert_eq!(location_url_suffix(Some(42), None), "#L42");
+        assert_eq!(location_url_suffix(Some(42), Some(10)), ":42:10");
+    }
+
+    #[test]
+    fn test_grep_style_line_reference() {
+        let matcher = test_matcher();
+        let cwd = PathBuf::from("/work");
+        let input = "src/main.rs:42: error";
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
+        assert!(result.contains("file://host/work/src/main.rs#L42"));
+        assert!(result.contains("src/main.rs:42"));
+    }
+
+    #[test]
+    fn test_grep_style_line_and_col_reference() {
+        let matcher = test_matcher();
+        let cwd = PathBuf::from("/work");
+        let input = "src/main.rs:42:10: error";
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
+        assert!(result.contains("file://host/work/src/main.rs:42:10"));
+        assert!(result.contains("src/main.rs:42:10"));
+    }
+
+    #[test]
+    fn test_no_match_inside_word() {
+        let matcher = test_matcher();
+        let cwd = PathBuf::from("/work");
+        // "src" is a prefix, but it shouldn't match inside "rsrc", even with a
+        // separator following it
+        let input = "please check rsrc/main.rs";
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_does_not_consume_past_prefix_without_separator() {
+        let matcher = build_matcher(&["lib".to_string()]);
+        let cwd = PathBuf::from("/work");
+        // "lib" is a cwd-entry prefix, but "library" is a different word and
+        // must not be glued into a hyperlink covering "rary" too.
+        let input = "please check the library docs";
+        let result = process_line(input, &matcher, "host", "/home/user", &cwd);
+        // "rary" must survive untouched after the hyperlinked "lib" --
+        // the old bug glued it into the match and hid it inside the escape.
+        assert!(result.contains("rary docs"));
+    }
+
+    #[test]
+    fn test_longest_prefix_wins_at_same_start() {
+        let matcher = build_matcher(&["/lib".to_string(), "/lib64".to_string()]);
+        let cwd = PathBuf::from("/work");
+        let result = process_line("/lib64/libc.so", &matcher, "host", "/home/user", &cwd);
+        assert!(result.contains("file://host/lib64/libc.so"));
+    }
+
+    // These exercise behavior that's gated on `cfg!(windows)` (UNC-root
+    // consumption, space-containing path bodies) and so can only actually
+    // run when compiled for Windows.
+    #[cfg(windows)]
+    mod windows_only {
+        use super::*;
+
+        #[test]
+        fn test_unc_path_is_fully_consumed() {
+            let matcher = build_matcher(&[r"\\".to_string()]);
+            let cwd = PathBuf::from(r"C:\work");
+            let result = process_line(
+                r"\\server\share\file.txt",
+                &matcher,
+                "host",
+                r"C:\Users\name",
+                &cwd,
+            );
+            assert!(result.contains("server/share/file.txt"));
+        }
+
+        #[test]
+        fn test_path_with_space_is_consumed_and_encoded() {
+            let matcher = build_matcher(&[r"C:".to_string()]);
+            let cwd = PathBuf::from(r"C:\work");
+            let result = process_line(
+                r"C:\Program Files\app.exe",
+                &matcher,
+                "host",
+                r"C:\Users\name",
+                &cwd,
+            );
+            assert!(result.contains("Program%20Files/app.exe"));
+        }
+    }
 }
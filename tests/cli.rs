@@ -0,0 +1,138 @@
+//! End-to-end tests that drive the compiled binary over stdin/stdout,
+//! covering the prefix discovery and stdin->stdout loop in `main` that the
+//! unit tests in `src/main.rs` bypass by calling `process_line` directly.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::fs;
+use tempfile::tempdir;
+
+const OSC: &str = "\x1b]";
+const BEL: &str = "\x07";
+
+/// Builds the exact OSC 8 byte sequence the binary is expected to emit, so
+/// tests can assert on it precisely instead of loosely checking that the
+/// escape marker and the path text both appear somewhere in the output.
+fn expected_hyperlink(url: &str, text: &str) -> String {
+    format!("{OSC}8;;{url}{BEL}{text}{OSC}8;;{BEL}")
+}
+
+fn local_hostname() -> String {
+    hostname::get().unwrap().to_string_lossy().into_owned()
+}
+
+/// The binary defaults to auto-detecting whether stdout is a terminal, and
+/// `assert_cmd` captures stdout as a pipe, so every test needs
+/// `--hyperlink=always` to force emission unless it's specifically testing
+/// the auto/never gating.
+fn hyperlink_cmd() -> Command {
+    let mut cmd = Command::cargo_bin("add-osc-8-hyperlink").unwrap();
+    cmd.arg("--hyperlink=always");
+    cmd
+}
+
+#[test]
+fn relative_entry_discovered_in_cwd_is_linked() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+    let hostname = local_hostname();
+    let abs_path = dir.path().join("notes.txt").to_string_lossy().into_owned();
+    let expected = format!(
+        "see {} for details\n",
+        expected_hyperlink(&format!("file://{hostname}{abs_path}"), "notes.txt")
+    );
+
+    hyperlink_cmd()
+        .current_dir(dir.path())
+        .write_stdin("see notes.txt for details\n")
+        .assert()
+        .success()
+        .stdout(expected);
+}
+
+#[test]
+fn home_expansion_uses_controlled_home() {
+    let home = tempdir().unwrap();
+    let cwd = tempdir().unwrap();
+
+    let hostname = local_hostname();
+    let abs_path = home.path().join("config.toml").to_string_lossy().into_owned();
+    let expected = format!(
+        "{}\n",
+        expected_hyperlink(&format!("file://{hostname}{abs_path}"), "~/config.toml")
+    );
+
+    hyperlink_cmd()
+        .current_dir(cwd.path())
+        .env("HOME", home.path())
+        .write_stdin("~/config.toml\n")
+        .assert()
+        .success()
+        .stdout(expected);
+}
+
+#[test]
+fn multiple_paths_on_one_line_both_get_linked() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("a.txt"), "").unwrap();
+    fs::write(dir.path().join("b.txt"), "").unwrap();
+
+    let hostname = local_hostname();
+    let a_path = dir.path().join("a.txt").to_string_lossy().into_owned();
+    let b_path = dir.path().join("b.txt").to_string_lossy().into_owned();
+    let expected = format!(
+        "diff {} {}\n",
+        expected_hyperlink(&format!("file://{hostname}{a_path}"), "a.txt"),
+        expected_hyperlink(&format!("file://{hostname}{b_path}"), "b.txt"),
+    );
+
+    hyperlink_cmd()
+        .current_dir(dir.path())
+        .write_stdin("diff a.txt b.txt\n")
+        .assert()
+        .success()
+        .stdout(expected);
+}
+
+#[test]
+fn line_with_no_paths_passes_through_unchanged() {
+    hyperlink_cmd()
+        .write_stdin("nothing to see here\n")
+        .assert()
+        .success()
+        .stdout("nothing to see here\n");
+}
+
+#[test]
+fn auto_mode_passes_lines_through_unchanged_when_not_a_terminal() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+    // No --hyperlink flag: defaults to `auto`, and a piped stdout isn't a
+    // terminal, so no OSC 8 escapes should appear.
+    Command::cargo_bin("add-osc-8-hyperlink")
+        .unwrap()
+        .current_dir(dir.path())
+        .write_stdin("see notes.txt for details\n")
+        .assert()
+        .success()
+        .stdout("see notes.txt for details\n")
+        .stdout(predicate::str::contains(OSC).not());
+}
+
+#[test]
+fn never_mode_suppresses_hyperlinks() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+    Command::cargo_bin("add-osc-8-hyperlink")
+        .unwrap()
+        .arg("--hyperlink=never")
+        .current_dir(dir.path())
+        .write_stdin("see notes.txt for details\n")
+        .assert()
+        .success()
+        .stdout("see notes.txt for details\n")
+        .stdout(predicate::str::contains(OSC).not());
+}